@@ -0,0 +1,290 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A rebatching adapter that re-chunks a [`SendableRecordBatchStream`] so every
+//! emitted [`RecordBatch`] targets a configurable row count or approximate byte
+//! budget, independent of the sizes produced upstream. Small upstream batches
+//! are coalesced together; oversized ones are split across several outputs.
+
+use std::cmp::{max, min};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use datatypes::arrow::array::UInt32Array;
+use datatypes::arrow::compute::{concat_batches, take};
+use datatypes::arrow::record_batch::RecordBatch as ArrowRecordBatch;
+use datatypes::schema::SchemaRef;
+use snafu::ResultExt;
+
+use crate::error::{self, Result};
+use crate::{RecordBatch, RecordBatchStream, SendableRecordBatchStream, Stream};
+
+/// Builds a [`CoalesceBatchesStreamAdapter`] with a row and/or byte target.
+pub struct CoalesceBatchesStreamAdapterBuilder {
+    stream: SendableRecordBatchStream,
+    target_rows: Option<usize>,
+    target_bytes: Option<usize>,
+}
+
+impl CoalesceBatchesStreamAdapterBuilder {
+    /// Targets `rows` rows per emitted batch. `rows` must be non-zero.
+    pub fn with_target_rows(mut self, rows: usize) -> Self {
+        self.target_rows = Some(rows);
+        self
+    }
+
+    /// Targets an approximate `bytes` of array memory per emitted batch.
+    /// `bytes` must be non-zero.
+    pub fn with_target_bytes(mut self, bytes: usize) -> Self {
+        self.target_bytes = Some(bytes);
+        self
+    }
+
+    /// Finalizes the adapter.
+    ///
+    /// Fails if a zero target was given: `target_met` would then consider an
+    /// empty buffer as already meeting the target, so the adapter would emit
+    /// `None` immediately and silently drop the whole upstream.
+    pub fn build(self) -> Result<CoalesceBatchesStreamAdapter> {
+        if self.target_rows == Some(0) || self.target_bytes == Some(0) {
+            return error::CreateRecordBatchesSnafu {
+                reason: "coalesce target_rows and target_bytes must be non-zero when set"
+                    .to_string(),
+            }
+            .fail();
+        }
+        let schema = self.stream.schema();
+        Ok(CoalesceBatchesStreamAdapter {
+            schema,
+            stream: self.stream,
+            target_rows: self.target_rows,
+            target_bytes: self.target_bytes,
+            buffer: Vec::new(),
+            done: false,
+        })
+    }
+}
+
+/// Wraps a [`SendableRecordBatchStream`] and emits batches sized to the
+/// configured target. See the module docs for the coalesce/split behavior.
+pub struct CoalesceBatchesStreamAdapter {
+    schema: SchemaRef,
+    stream: SendableRecordBatchStream,
+    target_rows: Option<usize>,
+    target_bytes: Option<usize>,
+    buffer: Vec<ArrowRecordBatch>,
+    done: bool,
+}
+
+impl CoalesceBatchesStreamAdapter {
+    /// Starts building an adapter over `stream`. At least one of
+    /// [`with_target_rows`](CoalesceBatchesStreamAdapterBuilder::with_target_rows)
+    /// or [`with_target_bytes`](CoalesceBatchesStreamAdapterBuilder::with_target_bytes)
+    /// should be set; otherwise the whole stream is buffered and flushed once.
+    pub fn builder(stream: SendableRecordBatchStream) -> CoalesceBatchesStreamAdapterBuilder {
+        CoalesceBatchesStreamAdapterBuilder {
+            stream,
+            target_rows: None,
+            target_bytes: None,
+        }
+    }
+
+    fn buffered_rows(&self) -> usize {
+        self.buffer.iter().map(|b| b.num_rows()).sum()
+    }
+
+    fn buffered_bytes(&self) -> usize {
+        self.buffer.iter().map(|b| b.get_array_memory_size()).sum()
+    }
+
+    fn target_met(&self) -> bool {
+        self.target_rows.is_some_and(|t| self.buffered_rows() >= t)
+            || self.target_bytes.is_some_and(|t| self.buffered_bytes() >= t)
+    }
+
+    /// Concatenates the buffer and emits one batch no larger than the target,
+    /// re-buffering any remainder.
+    fn emit_one(&mut self) -> Result<RecordBatch> {
+        let combined = concat_batches(self.schema.arrow_schema(), self.buffer.iter())
+            .context(error::ArrowSnafu)?;
+        self.buffer.clear();
+
+        let total_rows = combined.num_rows();
+        let split = self.split_point(&combined, total_rows);
+        let df_recordbatch = if split >= total_rows {
+            combined
+        } else {
+            // `slice` is zero-copy and keeps referencing the full backing
+            // buffer, so the remainder's `get_array_memory_size` would still
+            // report ~the pre-split size on the next round, overestimating
+            // bytes-per-row and collapsing subsequent splits. Compact it to a
+            // right-sized copy before buffering it for re-measurement.
+            let remainder = Self::compact(&combined.slice(split, total_rows - split))?;
+            self.buffer.push(remainder);
+            combined.slice(0, split)
+        };
+
+        Ok(RecordBatch {
+            schema: self.schema.clone(),
+            df_recordbatch,
+        })
+    }
+
+    /// Number of rows the next output batch should carry.
+    fn split_point(&self, combined: &ArrowRecordBatch, total_rows: usize) -> usize {
+        let mut split = total_rows;
+        if let Some(target) = self.target_rows {
+            split = min(split, target);
+        }
+        if let Some(target) = self.target_bytes {
+            let bytes = combined.get_array_memory_size();
+            if bytes > target && total_rows > 0 {
+                let per_row = max(1, bytes / total_rows);
+                split = min(split, max(1, target / per_row));
+            }
+        }
+        max(1, min(split, total_rows))
+    }
+
+    /// Materializes `batch` into freshly-allocated, right-sized column
+    /// buffers via an identity `take`, so its reported memory size reflects
+    /// only its own rows rather than a slice's shared backing buffer.
+    fn compact(batch: &ArrowRecordBatch) -> Result<ArrowRecordBatch> {
+        let indices = UInt32Array::from_iter_values(0..batch.num_rows() as u32);
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|col| take(col.as_ref(), &indices, None))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .context(error::ArrowSnafu)?;
+        ArrowRecordBatch::try_new(batch.schema(), columns).context(error::ArrowSnafu)
+    }
+}
+
+impl RecordBatchStream for CoalesceBatchesStreamAdapter {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for CoalesceBatchesStreamAdapter {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while !self.done && !self.target_met() {
+            match Pin::new(&mut self.stream).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(batch))) => self.buffer.push(batch.df_recordbatch),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => self.done = true,
+            }
+        }
+
+        if self.buffer.is_empty() {
+            // Only reachable once the upstream is exhausted and drained.
+            return Poll::Ready(None);
+        }
+        Poll::Ready(Some(self.emit_one()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::test_util::{batch_of_len, stream_of_lens as stream, test_schema};
+
+    async fn row_counts(mut s: SendableRecordBatchStream) -> Vec<usize> {
+        let mut out = Vec::new();
+        while let Some(b) = s.next().await {
+            out.push(b.unwrap().df_recordbatch.num_rows());
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_small_batches() {
+        let schema = test_schema();
+        let adapter = CoalesceBatchesStreamAdapter::builder(stream(&schema, &[1, 1, 1, 1]))
+            .with_target_rows(3)
+            .build()
+            .unwrap();
+        // 1+1+1 -> 3, then final flush of the remaining 1.
+        assert_eq!(row_counts(Box::pin(adapter)).await, vec![3, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_split_oversized_batch() {
+        let schema = test_schema();
+        let adapter = CoalesceBatchesStreamAdapter::builder(stream(&schema, &[10]))
+            .with_target_rows(4)
+            .build()
+            .unwrap();
+        assert_eq!(row_counts(Box::pin(adapter)).await, vec![4, 4, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_final_flush_and_schema_preserved() {
+        let schema = test_schema();
+        let adapter = CoalesceBatchesStreamAdapter::builder(stream(&schema, &[2, 2]))
+            .with_target_rows(10)
+            .build()
+            .unwrap();
+        assert_eq!(adapter.schema(), schema);
+        // Never reaches target, so everything is flushed as one final batch.
+        assert_eq!(row_counts(Box::pin(adapter)).await, vec![4]);
+    }
+
+    #[tokio::test]
+    async fn test_zero_target_rejected() {
+        let schema = test_schema();
+        let err = CoalesceBatchesStreamAdapter::builder(stream(&schema, &[3]))
+            .with_target_rows(0)
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, error::Error::CreateRecordBatches { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_split_oversized_batch_by_bytes_makes_progress() {
+        let schema = test_schema();
+        let adapter = CoalesceBatchesStreamAdapter::builder(stream(&schema, &[50]))
+            .with_target_bytes(64)
+            .build()
+            .unwrap();
+        let counts = row_counts(Box::pin(adapter)).await;
+        assert_eq!(counts.iter().sum::<usize>(), 50);
+        assert!(
+            counts.len() > 1,
+            "expected the oversized batch to be split: {counts:?}"
+        );
+    }
+
+    #[test]
+    fn test_compact_shrinks_sliced_remainder_memory_size() {
+        let schema = test_schema();
+        let full = batch_of_len(&schema, 2000).df_recordbatch;
+        let sliced = full.slice(1990, 10);
+        // The zero-copy slice still references the full backing buffer.
+        assert_eq!(sliced.get_array_memory_size(), full.get_array_memory_size());
+
+        let compacted = CoalesceBatchesStreamAdapter::compact(&sliced).unwrap();
+        assert_eq!(compacted.num_rows(), 10);
+        assert!(
+            compacted.get_array_memory_size() < full.get_array_memory_size(),
+            "compacting should drop the unused portion of the backing buffer"
+        );
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bridges between our [`SendableRecordBatchStream`] and the Arrow C Stream
+//! Interface ([`FFI_ArrowArrayStream`] / [`ArrowArrayStreamReader`]), so query
+//! results can be handed to any C-ABI Arrow consumer (PyArrow, DuckDB, ...)
+//! without copying the underlying buffers.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use datatypes::arrow::array::RecordBatchReader;
+use datatypes::arrow::datatypes::SchemaRef as ArrowSchemaRef;
+use datatypes::arrow::error::{ArrowError, Result as ArrowResult};
+use datatypes::arrow::ffi_stream::{ArrowArrayStreamReader, FFI_ArrowArrayStream};
+use datatypes::arrow::record_batch::RecordBatch as ArrowRecordBatch;
+use datatypes::schema::{Schema, SchemaRef};
+use futures::StreamExt;
+use snafu::ResultExt;
+use tokio::runtime::Handle;
+
+use crate::error::{self, Result};
+use crate::{RecordBatch, RecordBatchStream, SendableRecordBatchStream, Stream};
+
+/// Exports a [`SendableRecordBatchStream`] over the Arrow C Stream Interface.
+///
+/// The C interface is synchronous and pull-based, so every `get_next` call from
+/// the consumer blocks on the async stream using the supplied runtime
+/// [`Handle`]. Because [`Handle::block_on`] panics if it is called from within
+/// an async execution context, the resulting [`FFI_ArrowArrayStream`] must be
+/// driven from a thread that is **not** running on that runtime (a dedicated
+/// thread, or `spawn_blocking` bridged back to a plain thread). Errors surfaced
+/// by the async stream are carried through as [`ArrowError`]s whose message is
+/// preserved verbatim; arrow's FFI export layer copies that message into an
+/// owned, null-terminated string that outlives the reader, which is exactly the
+/// `get_last_error` contract the C consumer relies on.
+pub struct FfiRecordBatchStreamExporter {
+    stream: SendableRecordBatchStream,
+    handle: Handle,
+}
+
+impl FfiRecordBatchStreamExporter {
+    /// Creates an exporter that blocks on `stream` using `handle` whenever the
+    /// C consumer pulls the next batch.
+    pub fn new(stream: SendableRecordBatchStream, handle: Handle) -> Self {
+        Self { stream, handle }
+    }
+
+    /// Consumes the exporter and produces an [`FFI_ArrowArrayStream`] ready to be
+    /// passed across the C ABI.
+    pub fn into_ffi_stream(self) -> FFI_ArrowArrayStream {
+        let reader = BlockingStreamReader {
+            schema: self.stream.schema().arrow_schema().clone(),
+            stream: self.stream,
+            handle: self.handle,
+        };
+        FFI_ArrowArrayStream::new(Box::new(reader))
+    }
+}
+
+/// A synchronous [`RecordBatchReader`] that drives an async stream by blocking on
+/// the provided runtime handle. This is the bridge arrow's FFI exporter expects.
+struct BlockingStreamReader {
+    schema: ArrowSchemaRef,
+    stream: SendableRecordBatchStream,
+    handle: Handle,
+}
+
+impl Iterator for BlockingStreamReader {
+    type Item = ArrowResult<ArrowRecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.handle.block_on(self.stream.next());
+        next.map(|recordbatch| {
+            // Preserve the full error message so it survives into the C
+            // `get_last_error` string, rather than collapsing it to an empty
+            // external prefix.
+            recordbatch
+                .map(|b| b.df_recordbatch)
+                .map_err(|e| ArrowError::External(e.to_string(), Box::new(e)))
+        })
+    }
+}
+
+impl RecordBatchReader for BlockingStreamReader {
+    fn schema(&self) -> ArrowSchemaRef {
+        self.schema.clone()
+    }
+}
+
+/// Imports an incoming Arrow C stream and exposes it as a [`RecordBatchStream`],
+/// mirroring [`crate::adapter::RecordBatchStreamAdapter`].
+///
+/// Schema conversion happens eagerly at construction time so that a malformed
+/// `get_schema` is reported before the first batch is pulled.
+pub struct FfiRecordBatchStreamImporter {
+    schema: SchemaRef,
+    reader: ArrowArrayStreamReader,
+}
+
+impl FfiRecordBatchStreamImporter {
+    /// Builds an importer from a consumer-provided [`FFI_ArrowArrayStream`],
+    /// converting its schema eagerly.
+    pub fn try_new(stream: FFI_ArrowArrayStream) -> Result<Self> {
+        let reader = ArrowArrayStreamReader::try_new(stream).context(error::ArrowSnafu)?;
+        let schema = Arc::new(
+            Schema::try_from(reader.schema()).context(error::SchemaConversionSnafu)?,
+        );
+        Ok(Self { schema, reader })
+    }
+}
+
+impl RecordBatchStream for FfiRecordBatchStreamImporter {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for FfiRecordBatchStreamImporter {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // The C stream is synchronous and pull-based; each pull resolves
+        // immediately, so we never yield `Pending`.
+        let schema = self.schema();
+        Poll::Ready(self.reader.next().map(|df_recordbatch| {
+            // `ArrowArrayStreamReader::next` yields `ArrowError` directly, so
+            // route it through the dedicated `Arrow` variant to preserve the
+            // structured source chain, rather than stringifying it away.
+            let df_recordbatch = df_recordbatch.context(error::ArrowSnafu)?;
+            Ok(RecordBatch {
+                schema,
+                df_recordbatch,
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::error::Error;
+    use crate::test_util::{batch, test_schema, VecStream};
+
+    /// Exports `stream` and drains the imported side on a dedicated thread, off
+    /// the async runtime — the only safe way to drive the synchronous C bridge,
+    /// since `Handle::block_on` panics inside a runtime context.
+    fn round_trip(
+        handle: Handle,
+        stream: SendableRecordBatchStream,
+    ) -> Vec<Result<RecordBatch>> {
+        std::thread::spawn(move || {
+            let ffi = FfiRecordBatchStreamExporter::new(stream, handle).into_ffi_stream();
+            let mut imported = FfiRecordBatchStreamImporter::try_new(ffi).unwrap();
+            let mut out = Vec::new();
+            while let Some(item) = futures::executor::block_on(imported.next()) {
+                out.push(item);
+            }
+            out
+        })
+        .join()
+        .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_export_import_round_trip() {
+        let schema = test_schema();
+        let stream = Box::pin(VecStream {
+            schema: schema.clone(),
+            items: vec![Ok(batch(&schema, &[1, 2, 3])), Ok(batch(&schema, &[4, 5]))].into(),
+        });
+
+        let rows: Vec<_> = round_trip(Handle::current(), stream)
+            .into_iter()
+            .map(|b| b.unwrap().df_recordbatch.num_rows())
+            .collect();
+        assert_eq!(rows, vec![3, 2]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_error_message_survives_ffi() {
+        let schema = test_schema();
+        let err = error::CreateRecordBatchesSnafu {
+            reason: "boom from upstream".to_string(),
+        }
+        .build();
+        let stream = Box::pin(VecStream {
+            schema: schema.clone(),
+            items: vec![Err(err)].into(),
+        });
+
+        let mut results = round_trip(Handle::current(), stream);
+        let Error::Arrow { source, .. } = results.remove(0).unwrap_err() else {
+            panic!("expected Arrow error");
+        };
+        // The original message must cross the C boundary intact.
+        let message = source.to_string();
+        assert!(message.contains("boom from upstream"), "got: {message}");
+    }
+}
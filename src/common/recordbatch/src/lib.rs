@@ -0,0 +1,46 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod adapter;
+pub mod coalesce;
+pub mod encoder;
+pub mod error;
+pub mod ffi;
+pub mod memory;
+pub mod metrics;
+#[cfg(test)]
+pub(crate) mod test_util;
+
+use std::pin::Pin;
+
+use datafusion::physical_plan::SendableRecordBatchStream as DfSendableRecordBatchStream;
+use datatypes::arrow::record_batch::RecordBatch as DfRecordBatch;
+use datatypes::schema::SchemaRef;
+pub use futures::Stream;
+
+use crate::error::Result;
+
+/// A record batch paired with the Greptime schema it was produced under.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordBatch {
+    pub schema: SchemaRef,
+    pub df_recordbatch: DfRecordBatch,
+}
+
+/// A [`Stream`] of [`RecordBatch`]es that also exposes its schema.
+pub trait RecordBatchStream: Stream<Item = Result<RecordBatch>> {
+    fn schema(&self) -> SchemaRef;
+}
+
+pub type SendableRecordBatchStream = Pin<Box<dyn RecordBatchStream + Send>>;
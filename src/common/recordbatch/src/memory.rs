@@ -0,0 +1,136 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An in-memory [`RecordBatchStream`] backed by an owned `Vec<RecordBatch>`.
+//!
+//! Because the materialized batches are shared behind an [`Arc`], the stream is
+//! cheap to clone and can be [`replay`](MemoryRecordBatchStream::replay)ed any
+//! number of times. This serves both as a test double and as a small caching
+//! primitive (e.g. serving the same subquery result to several subscribers).
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use datatypes::schema::SchemaRef;
+use futures::StreamExt;
+
+use crate::error::Result;
+use crate::{RecordBatch, RecordBatchStream, SendableRecordBatchStream, Stream};
+
+/// A replayable stream that yields a fixed set of batches in order.
+#[derive(Clone)]
+pub struct MemoryRecordBatchStream {
+    schema: SchemaRef,
+    batches: Arc<Vec<RecordBatch>>,
+    index: usize,
+}
+
+impl MemoryRecordBatchStream {
+    /// Creates a stream over `batches`, yielded in order under `schema`.
+    pub fn new(schema: SchemaRef, batches: Vec<RecordBatch>) -> Self {
+        Self {
+            schema,
+            batches: Arc::new(batches),
+            index: 0,
+        }
+    }
+
+    /// Returns a fresh stream over the same materialized batches, rewound to the
+    /// beginning. The underlying batches are shared, not copied.
+    pub fn replay(&self) -> Self {
+        Self {
+            schema: self.schema.clone(),
+            batches: self.batches.clone(),
+            index: 0,
+        }
+    }
+}
+
+impl RecordBatchStream for MemoryRecordBatchStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for MemoryRecordBatchStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.index < self.batches.len() {
+            let batch = self.batches[self.index].clone();
+            self.index += 1;
+            Poll::Ready(Some(Ok(batch)))
+        } else {
+            Poll::Ready(None)
+        }
+    }
+
+    // Unlike the lazy `AsyncRecordBatchStreamAdapter`, the number of remaining
+    // batches is known exactly.
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.batches.len() - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+/// Drains `stream` into an owned, replayable [`MemoryRecordBatchStream`].
+pub async fn collect_to_memory(
+    mut stream: SendableRecordBatchStream,
+) -> Result<MemoryRecordBatchStream> {
+    let schema = stream.schema();
+    let mut batches = Vec::new();
+    while let Some(batch) = stream.next().await {
+        batches.push(batch?);
+    }
+    Ok(MemoryRecordBatchStream::new(schema, batches))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::test_util::{batch_of_len as batch, test_schema};
+
+    #[tokio::test]
+    async fn test_exact_size_hint_and_replay() {
+        let schema = test_schema();
+        let batches = vec![batch(&schema, 1), batch(&schema, 2)];
+        let stream = MemoryRecordBatchStream::new(schema.clone(), batches);
+
+        assert_eq!(stream.size_hint(), (2, Some(2)));
+
+        let mut draining = stream.clone();
+        assert!(draining.next().await.is_some());
+        assert_eq!(draining.size_hint(), (1, Some(1)));
+        assert!(draining.next().await.is_some());
+        assert_eq!(draining.size_hint(), (0, Some(0)));
+        assert!(draining.next().await.is_none());
+
+        // A replay rewinds to the beginning over the shared batches.
+        let replayed: Vec<_> = stream.replay().collect().await;
+        assert_eq!(replayed.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_collect_to_memory() {
+        let schema = test_schema();
+        let source = MemoryRecordBatchStream::new(schema.clone(), vec![batch(&schema, 4)]);
+        let collected = collect_to_memory(Box::pin(source)).await.unwrap();
+        assert_eq!(collected.schema(), schema);
+        assert_eq!(collected.size_hint(), (1, Some(1)));
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A transparent instrumentation wrapper that records execution metrics for a
+//! [`SendableRecordBatchStream`] without altering its output. The metrics are
+//! exposed through a cloneable handle so a caller can observe live progress
+//! while the stream is still draining, or attach the totals to EXPLAIN ANALYZE.
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use datatypes::schema::SchemaRef;
+
+use crate::error::Result;
+use crate::{RecordBatch, RecordBatchStream, SendableRecordBatchStream, Stream};
+
+#[derive(Default)]
+struct MetricsInner {
+    output_rows: AtomicUsize,
+    output_bytes: AtomicUsize,
+    poll_nanos: AtomicU64,
+    time_to_first_batch_nanos: AtomicU64,
+    first_batch_seen: AtomicBool,
+}
+
+/// A cloneable, atomically-updated view of a stream's execution metrics. Cloning
+/// shares the same underlying counters, so readers see updates as they happen.
+#[derive(Clone, Default)]
+pub struct RecordBatchStreamMetrics {
+    inner: Arc<MetricsInner>,
+}
+
+impl RecordBatchStreamMetrics {
+    /// Cumulative number of output rows observed so far.
+    pub fn output_rows(&self) -> usize {
+        self.inner.output_rows.load(Ordering::Relaxed)
+    }
+
+    /// Cumulative output bytes, summed from each batch's array memory size.
+    pub fn output_bytes(&self) -> usize {
+        self.inner.output_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Wall-clock time spent inside `poll_next` so far.
+    pub fn elapsed_poll(&self) -> Duration {
+        Duration::from_nanos(self.inner.poll_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Time from adapter creation to the first emitted batch, or `None` if no
+    /// batch has been produced yet.
+    pub fn time_to_first_batch(&self) -> Option<Duration> {
+        self.inner
+            .first_batch_seen
+            .load(Ordering::Relaxed)
+            .then(|| Duration::from_nanos(self.inner.time_to_first_batch_nanos.load(Ordering::Relaxed)))
+    }
+}
+
+/// Wraps a [`SendableRecordBatchStream`], instrumenting only the poll path and
+/// forwarding [`schema`](RecordBatchStream::schema) and
+/// [`size_hint`](Stream::size_hint) unchanged.
+pub struct MeteredRecordBatchStreamAdapter {
+    stream: SendableRecordBatchStream,
+    metrics: RecordBatchStreamMetrics,
+    start: Instant,
+}
+
+impl MeteredRecordBatchStreamAdapter {
+    pub fn new(stream: SendableRecordBatchStream) -> Self {
+        Self {
+            stream,
+            metrics: RecordBatchStreamMetrics::default(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns a cloneable handle to the live metrics of this stream.
+    pub fn metrics(&self) -> RecordBatchStreamMetrics {
+        self.metrics.clone()
+    }
+}
+
+impl RecordBatchStream for MeteredRecordBatchStreamAdapter {
+    fn schema(&self) -> SchemaRef {
+        self.stream.schema()
+    }
+}
+
+impl Stream for MeteredRecordBatchStreamAdapter {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll_start = Instant::now();
+        let result = Pin::new(&mut self.stream).poll_next(cx);
+        let inner = &self.metrics.inner;
+        inner
+            .poll_nanos
+            .fetch_add(poll_start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+
+        if let Poll::Ready(Some(Ok(batch))) = &result {
+            inner
+                .output_rows
+                .fetch_add(batch.df_recordbatch.num_rows(), Ordering::Relaxed);
+            inner
+                .output_bytes
+                .fetch_add(batch.df_recordbatch.get_array_memory_size(), Ordering::Relaxed);
+            if !inner.first_batch_seen.swap(true, Ordering::Relaxed) {
+                inner
+                    .time_to_first_batch_nanos
+                    .store(self.start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+            }
+        }
+
+        result
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::test_util::{batch_of_len as batch, test_schema, VecStream};
+
+    #[tokio::test]
+    async fn test_metrics_accumulate() {
+        let schema = test_schema();
+        let adapter = MeteredRecordBatchStreamAdapter::new(Box::pin(VecStream {
+            schema: schema.clone(),
+            items: vec![Ok(batch(&schema, 3)), Ok(batch(&schema, 2))].into(),
+        }));
+        let metrics = adapter.metrics();
+        assert_eq!(metrics.output_rows(), 0);
+        assert!(metrics.time_to_first_batch().is_none());
+
+        let mut adapter = Box::pin(adapter);
+        adapter.next().await.unwrap().unwrap();
+        assert_eq!(metrics.output_rows(), 3);
+        assert!(metrics.output_bytes() > 0);
+        let ttfb = metrics.time_to_first_batch().expect("first batch seen");
+
+        adapter.next().await.unwrap().unwrap();
+        assert_eq!(metrics.output_rows(), 5);
+        // Time-to-first-batch is latched on the first batch and not
+        // overwritten by later polls. Comparing against the same stored
+        // value (rather than racing it against another independent clock)
+        // keeps this assertion deterministic.
+        assert_eq!(metrics.time_to_first_batch(), Some(ttfb));
+
+        assert!(adapter.next().await.is_none());
+        assert_eq!(metrics.time_to_first_batch(), Some(ttfb));
+    }
+}
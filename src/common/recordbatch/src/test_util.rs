@@ -0,0 +1,92 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shared fixtures for the stream adapter tests in this crate, so every
+//! module tests against the same schema, batch and stream double instead of
+//! a divergent copy.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use datatypes::arrow::array::Int32Array;
+use datatypes::arrow::datatypes::{DataType, Field, Schema as ArrowSchema};
+use datatypes::arrow::record_batch::RecordBatch as ArrowRecordBatch;
+use datatypes::schema::{Schema, SchemaRef};
+
+use crate::error::Result;
+use crate::{RecordBatch, RecordBatchStream, SendableRecordBatchStream, Stream};
+
+/// A single non-nullable `Int32` column named `v`.
+pub(crate) fn test_schema() -> SchemaRef {
+    let arrow_schema = Arc::new(ArrowSchema::new(vec![Field::new(
+        "v",
+        DataType::Int32,
+        false,
+    )]));
+    Arc::new(Schema::try_from(arrow_schema).unwrap())
+}
+
+/// Builds a single-column batch holding `values` under `schema`.
+pub(crate) fn batch(schema: &SchemaRef, values: &[i32]) -> RecordBatch {
+    let array = Arc::new(Int32Array::from(values.to_vec()));
+    RecordBatch {
+        schema: schema.clone(),
+        df_recordbatch: ArrowRecordBatch::try_new(schema.arrow_schema().clone(), vec![array])
+            .unwrap(),
+    }
+}
+
+/// Builds a batch of `len` rows holding `0..len`.
+pub(crate) fn batch_of_len(schema: &SchemaRef, len: usize) -> RecordBatch {
+    batch(schema, &(0..len as i32).collect::<Vec<_>>())
+}
+
+/// A minimal in-test stream that yields pre-built items in order.
+pub(crate) struct VecStream {
+    pub(crate) schema: SchemaRef,
+    pub(crate) items: VecDeque<Result<RecordBatch>>,
+}
+
+impl RecordBatchStream for VecStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for VecStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.items.pop_front())
+    }
+}
+
+/// Wraps one batch per entry of `batches` into a [`SendableRecordBatchStream`].
+pub(crate) fn stream(schema: &SchemaRef, batches: &[&[i32]]) -> SendableRecordBatchStream {
+    Box::pin(VecStream {
+        schema: schema.clone(),
+        items: batches.iter().map(|v| Ok(batch(schema, v))).collect(),
+    })
+}
+
+/// Wraps one batch per length in `lens` (each holding `0..len`) into a
+/// [`SendableRecordBatchStream`].
+pub(crate) fn stream_of_lens(schema: &SchemaRef, lens: &[usize]) -> SendableRecordBatchStream {
+    Box::pin(VecStream {
+        schema: schema.clone(),
+        items: lens.iter().map(|&l| Ok(batch_of_len(schema, l))).collect(),
+    })
+}